@@ -1,33 +1,34 @@
 use async_trait::async_trait;
-use axum::{FromRequest, Handler};
-use http::Error;
-use hyper::body::to_bytes;
-use hyper::{Body, Request, Response}; // Replace with actual crate name
+use axum::{FromRequest, Handler, MissingHeader};
+use http_body_util::BodyExt;
+use hyper::{Body, Request}; // Replace with actual crate name
 
-// A sample type to extract from the request
+// A sample type to extract from the request. Unlike the old version this
+// rejects with 400 instead of silently defaulting to "guest".
 pub struct UserId(pub String);
 
 #[async_trait]
 impl FromRequest for UserId {
-    async fn from_request(req: &mut Request<Body>) -> Self {
-        let user_id = req
-            .headers()
+    type Rejection = MissingHeader;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        req.headers()
             .get("x-user-id")
             .and_then(|h| h.to_str().ok())
-            .unwrap_or("guest")
-            .to_string();
-        UserId(user_id)
+            .map(|v| UserId(v.to_string()))
+            .ok_or(MissingHeader("x-user-id"))
     }
 }
 
-// Simple handler without FromRequest
-async fn hello(_: Request<Body>) -> Result<Response<Body>, Error> {
-    Ok(Response::new(Body::from("Hello, world!")))
+// Simple handler without FromRequest; `IntoResponse` turns the plain
+// `&'static str` into a 200 `text/plain` response.
+async fn hello(_: Request<Body>) -> &'static str {
+    "Hello, world!"
 }
 
 // Handler that uses FromRequest
-async fn greet(_req: Request<Body>, user: UserId) -> Result<Response<Body>, Error> {
-    Ok(Response::new(Body::from(format!("Hello, {}!", user.0))))
+async fn greet(_req: Request<Body>, user: UserId) -> String {
+    format!("Hello, {}!", user.0)
 }
 
 #[tokio::main]
@@ -35,7 +36,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Calling hello handler:");
     let req1 = Request::new(Body::empty());
     let res1 = hello.call(req1).await?;
-    let body1 = to_bytes(res1.into_body()).await?;
+    let body1 = res1.into_body().collect().await?.to_bytes();
     println!("{}", std::str::from_utf8(&body1)?);
 
     println!("\nCalling greet handler:");
@@ -43,7 +44,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .header("x-user-id", "anand123")
         .body(Body::empty())?;
     let res2 = greet.call(req2).await?;
-    let body2 = to_bytes(res2.into_body()).await?;
+    let body2 = res2.into_body().collect().await?.to_bytes();
     println!("{}", std::str::from_utf8(&body2)?);
 
     Ok(())
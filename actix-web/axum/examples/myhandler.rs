@@ -1,40 +1,36 @@
 use async_trait::async_trait;
-use axum::{Body, FromRequest, Handler};
-use http::{Error, Request, Response};
-use hyper::body::to_bytes;
+use axum::{Body, FromRequest, Handler, MissingHeader};
+use http::Request;
+use http_body_util::BodyExt;
 
-async fn hello(_req: Request<Body>) -> Result<Response<Body>, Error> {
-    Ok(Response::new(Body::from("Hello from hello handler")))
+async fn hello(_req: Request<Body>) -> &'static str {
+    "Hello from hello handler"
 }
 
 pub struct UserId(pub String);
 
 #[async_trait]
 impl FromRequest for UserId {
-    async fn from_request(req: &mut Request<Body>) -> Self {
-        let user_id = req
-            .headers()
+    type Rejection = MissingHeader;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        req.headers()
             .get("x-user-id")
             .and_then(|h| h.to_str().ok())
-            .unwrap_or("guest")
-            .to_string();
-
-        UserId(user_id)
+            .map(|v| UserId(v.to_string()))
+            .ok_or(MissingHeader("x-user-id"))
     }
 }
 
-async fn hello_with_extractor(_req: Request<Body>, user: UserId) -> Result<Response<Body>, Error> {
-    Ok(Response::new(Body::from(format!(
-        "Hello from hello with extractor handler {}",
-        user.0
-    ))))
+async fn hello_with_extractor(_req: Request<Body>, user: UserId) -> String {
+    format!("Hello from hello with extractor handler {}", user.0)
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let req = Request::new(Body::empty());
     let res = hello.call(req).await?;
-    let body = to_bytes(res.into_body()).await?;
+    let body = res.into_body().collect().await?.to_bytes();
     println!(
         "{:?}",
         std::str::from_utf8(&body).expect("Not a valid utf-8")
@@ -43,7 +39,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .header("x-user-id", "ElonMusk")
         .body(Body::empty())?;
     let res1 = hello_with_extractor.call(req1).await?;
-    let body1 = to_bytes(res1.into_body()).await?;
+    let body1 = res1.into_body().collect().await?.to_bytes();
     println!(
         "{:?}",
         std::str::from_utf8(&body1).expect("Not a valid utf-8")
@@ -1,17 +1,20 @@
-// use bytes::Bytes;
 // use http::{Request, Response, StatusCode};
 // use hyper::Body;
-// use std::{convert::Infallible, net::SocketAddr};
+// use std::net::SocketAddr;
 // use tokio::fs::File;
 // use tokio_util::io::ReaderStream;
 // use tower::ServiceBuilder;
 
-// use axum::Handler;
+// use axum::{boxed, BoxBody, Handler};
+// use http_body_util::StreamBody;
 
-// async fn stream_file_handler(_req: Request<Body>) -> Result<Response<Body>, Error> {
+// // `boxed` erases the concrete streamed body behind `BoxBody`, so this
+// // handler can sit next to ones returning `&'static str` or `String`
+// // without `Route`/`Handler` needing to know about file streaming at all.
+// async fn stream_file_handler(_req: Request<Body>) -> Result<Response<BoxBody>, Error> {
 //     let file = File::open("Cargo.toml").await?; // Change to a large file if needed
 //     let stream = ReaderStream::new(file);
-//     let body = Body::wrap_stream(stream);
+//     let body = boxed(StreamBody::new(stream));
 
 //     let response = Response::builder()
 //         .status(StatusCode::OK)
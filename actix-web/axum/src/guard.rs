@@ -0,0 +1,96 @@
+//! Request guards, borrowed from actix-web's `guard` module: predicates a
+//! route can attach so two handlers can share the same path and dispatch
+//! on something the router's static path matching can't express, like an
+//! `Accept` header or a virtual host.
+
+use crate::Body;
+use http::{HeaderValue, Method, Request};
+
+pub trait Guard: Send + Sync {
+    fn check(&self, req: &Request<Body>) -> bool;
+}
+
+/// Matches requests using the given HTTP method.
+pub struct MethodGuard(Method);
+
+impl Guard for MethodGuard {
+    fn check(&self, req: &Request<Body>) -> bool {
+        req.method() == self.0
+    }
+}
+
+pub fn method(method: Method) -> MethodGuard {
+    MethodGuard(method)
+}
+
+/// Matches requests carrying a header, optionally with a specific value.
+/// With `value: None` this only checks presence.
+pub struct Header {
+    name: &'static str,
+    value: Option<HeaderValue>,
+}
+
+impl Guard for Header {
+    fn check(&self, req: &Request<Body>) -> bool {
+        match req.headers().get(self.name) {
+            Some(actual) => match &self.value {
+                Some(expected) => actual == expected,
+                None => true,
+            },
+            None => false,
+        }
+    }
+}
+
+pub fn header(name: &'static str) -> Header {
+    Header { name, value: None }
+}
+
+pub fn header_value(name: &'static str, value: &'static str) -> Header {
+    Header {
+        name,
+        value: Some(HeaderValue::from_static(value)),
+    }
+}
+
+/// Matches requests whose `Host` header equals the given value.
+pub struct Host(String);
+
+impl Guard for Host {
+    fn check(&self, req: &Request<Body>) -> bool {
+        req.headers()
+            .get(http::header::HOST)
+            .and_then(|h| h.to_str().ok())
+            == Some(self.0.as_str())
+    }
+}
+
+pub fn host(host: impl Into<String>) -> Host {
+    Host(host.into())
+}
+
+/// Passes when at least one of the wrapped guards passes.
+pub struct Any(Vec<Box<dyn Guard>>);
+
+impl Guard for Any {
+    fn check(&self, req: &Request<Body>) -> bool {
+        self.0.iter().any(|guard| guard.check(req))
+    }
+}
+
+pub fn any(guards: Vec<Box<dyn Guard>>) -> Any {
+    Any(guards)
+}
+
+/// Passes only when every wrapped guard passes.
+pub struct All(Vec<Box<dyn Guard>>);
+
+impl Guard for All {
+    fn check(&self, req: &Request<Body>) -> bool {
+        self.0.iter().all(|guard| guard.check(req))
+    }
+}
+
+pub fn all(guards: Vec<Box<dyn Guard>>) -> All {
+    All(guards)
+}
@@ -1,21 +1,70 @@
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures_util::future::ok;
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
 use http::{Error, Method, Request, Response, StatusCode};
 pub use hyper::body::Body;
-use std::{future::Future, task::Poll};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full};
+use serde::de::DeserializeOwned;
+use std::{collections::HashMap, future::Future, sync::Arc, task::Poll};
 use tower::Service;
 
+pub mod guard;
+pub use guard::Guard;
+
+/// A type-erased, boxed *response* body: any `http_body::Body<Data =
+/// Bytes>` can be turned into one via [`boxed`], so a handler can hand
+/// back a streaming body (file reads, SSE, ...) via [`IntoResponse`]
+/// without every concrete response type needing to agree on one body
+/// type. This is the shape hyper 1.0's `http-body`/`http-body-util` split
+/// is steering towards, in place of `hyper::Body` for outgoing responses.
+///
+/// This only covers responses. `Body` (hyper 0.14's, re-exported above)
+/// stays the concrete *request* body type throughout `FromRequest`,
+/// `Handler`, and every `Service` impl: hyper 0.14's `Body` implements the
+/// old 0.4 `http_body::Body`, not the 1.0 trait `boxed` uses here, so
+/// genuinely threading a `B: http_body::Body` parameter through the
+/// request side would mean first replacing `Body` itself with an adapter
+/// over hyper 0.14's trait - a bigger migration than this erasure, and one
+/// this crate hasn't taken on yet.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+pub type BoxBody = UnsyncBoxBody<Bytes, BoxError>;
+
+/// Erases a concrete response body behind [`BoxBody`].
+pub fn boxed<B>(body: B) -> BoxBody
+where
+    B: http_body::Body<Data = Bytes> + Send + Sync + 'static,
+    B::Error: Into<BoxError>,
+{
+    body.map_err(Into::into).boxed_unsync()
+}
+
 #[derive(Clone)]
-pub struct App<R> {
+pub struct App<R = Router> {
     router: R,
 }
 
 #[derive(Clone, Copy)]
 pub struct EmptyRouter(());
 
+impl EmptyRouter {
+    /// The 404 service every `.route(a).route(b)` chain (see
+    /// [`EmptyRouter::route`]) falls back to once nothing in the chain
+    /// matches.
+    pub fn new() -> Self {
+        EmptyRouter(())
+    }
+}
+
+impl Default for EmptyRouter {
+    fn default() -> Self {
+        EmptyRouter::new()
+    }
+}
+
 impl Service<Request<Body>> for EmptyRouter {
-    type Response = Response<Body>;
+    type Response = Response<BoxBody>;
     type Error = Error;
     type Future = futures_util::future::Ready<Result<Self::Response, Self::Error>>;
 
@@ -27,119 +76,904 @@ impl Service<Request<Body>> for EmptyRouter {
     }
 
     fn call(&mut self, _req: Request<Body>) -> Self::Future {
-        let mut res = Response::new(Body::empty());
+        let mut res = Response::new(boxed(Full::new(Bytes::new())));
         *res.status_mut() = StatusCode::NOT_FOUND;
         futures_util::future::ready(Ok(res))
     }
 }
-// implementing struct concrete App with EmptyRouter
-impl App<EmptyRouter> {
+
+/// One path segment of a compiled route pattern, e.g. `/users/:id/*rest`
+/// compiles to `[Static("users"), Param("id"), Wildcard("rest")]`.
+#[derive(Clone, Debug)]
+enum Segment {
+    Static(Bytes),
+    Param(String),
+    Wildcard(String),
+}
+
+fn parse_pattern(path: &str) -> Vec<Segment> {
+    path.split('/')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            if let Some(name) = part.strip_prefix(':') {
+                Segment::Param(name.to_string())
+            } else if let Some(name) = part.strip_prefix('*') {
+                Segment::Wildcard(name.to_string())
+            } else {
+                Segment::Static(Bytes::copy_from_slice(part.as_bytes()))
+            }
+        })
+        .collect()
+}
+
+/// Try to match `path` against a compiled pattern, returning the captured
+/// params (in pattern order) on success.
+fn match_segments(segments: &[Segment], path: &str) -> Option<Vec<(String, String)>> {
+    let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+    let mut captures = Vec::new();
+    let mut parts = parts.into_iter();
+
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Wildcard(name) => {
+                let rest: Vec<&str> = parts.by_ref().collect();
+                captures.push((name.clone(), rest.join("/")));
+                if i != segments.len() - 1 {
+                    return None;
+                }
+                return Some(captures);
+            }
+            Segment::Static(expected) => {
+                let part = parts.next()?;
+                if part.as_bytes() != expected.as_ref() {
+                    return None;
+                }
+            }
+            Segment::Param(name) => {
+                let part = parts.next()?;
+                captures.push((name.clone(), part.to_string()));
+            }
+        }
+    }
+
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(captures)
+}
+
+type BoxedHandlerFn =
+    Arc<dyn Fn(Request<Body>) -> BoxFuture<'static, Result<Response<BoxBody>, Error>> + Send + Sync>;
+
+struct RouteEntry {
+    method: Method,
+    segments: Vec<Segment>,
+    guards: Vec<Arc<dyn Guard>>,
+    handler: BoxedHandlerFn,
+}
+
+impl Clone for RouteEntry {
+    fn clone(&self) -> Self {
+        RouteEntry {
+            method: self.method.clone(),
+            segments: self.segments.clone(),
+            guards: self.guards.clone(),
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+/// A table-driven router: each registered route carries a method, a
+/// compiled path pattern, and a boxed handler. Routes are tried in
+/// registration order and the first match wins.
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: Vec<RouteEntry>,
+    fallback: Option<EmptyRouter>,
+}
+
+impl Service<Request<Body>> for Router {
+    type Response = Response<BoxBody>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        for route in &self.routes {
+            if req.method() != route.method {
+                continue;
+            }
+            if !route.guards.iter().all(|guard| guard.check(&req)) {
+                continue;
+            }
+            if let Some(captures) = match_segments(&route.segments, req.uri().path()) {
+                req.extensions_mut().insert(CapturedParams(captures));
+                let handler = route.handler.clone();
+                return Box::pin(async move { handler(req).await });
+            }
+        }
+
+        let mut fallback = self.fallback.unwrap_or(EmptyRouter(()));
+        Box::pin(async move { fallback.call(req).await })
+    }
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router {
+            routes: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Registers a handler for `path` under `method`. `path` may contain
+    /// named params (`:id`) and a single trailing wildcard (`*rest`).
+    ///
+    /// Like a [`Route`] chain, a `Router` is a plain value usable on its own
+    /// (not just through `App`), so a sub-router built up this way can be
+    /// mounted under a prefix with [`nest`].
+    pub fn route<H, Out>(self, path: &str, method: Method, handler: H) -> Self
+    where
+        H: Handler<Out> + Clone + Send + Sync + 'static,
+        Out: Send + 'static,
+    {
+        self.route_with_guards(path, method, Vec::new(), handler)
+    }
+
+    /// Like [`route`](Self::route), but the route is only selected when
+    /// every guard in `guards` also passes. Lets two handlers share the
+    /// same path and method and dispatch on something a guard can see,
+    /// like an `Accept` header, that the path pattern can't express.
+    pub fn route_with_guards<H, Out>(
+        mut self,
+        path: &str,
+        method: Method,
+        guards: Vec<Box<dyn Guard>>,
+        handler: H,
+    ) -> Self
+    where
+        H: Handler<Out> + Clone + Send + Sync + 'static,
+        Out: Send + 'static,
+    {
+        let segments = parse_pattern(path);
+        let guards: Vec<Arc<dyn Guard>> = guards.into_iter().map(Arc::from).collect();
+        let handler: BoxedHandlerFn = Arc::new(move |req| {
+            let handler = handler.clone();
+            Box::pin(async move { handler.call(req).await })
+        });
+        self.routes.push(RouteEntry {
+            method,
+            segments,
+            guards,
+            handler,
+        });
+        self
+    }
+}
+
+// `App::new()` starts from an empty routing table, which falls straight
+// through to the 404 `EmptyRouter` behavior until routes are registered.
+impl App<Router> {
     pub fn new() -> Self {
         App {
-            router: EmptyRouter(()),
+            router: Router::new(),
+        }
+    }
+
+    /// Registers a handler for `path` under `method`. See [`Router::route`].
+    pub fn route<H, Out>(self, path: &str, method: Method, handler: H) -> Self
+    where
+        H: Handler<Out> + Clone + Send + Sync + 'static,
+        Out: Send + 'static,
+    {
+        App {
+            router: self.router.route(path, method, handler),
         }
     }
+
+    /// Registers a guarded handler for `path` under `method`. See
+    /// [`Router::route_with_guards`].
+    pub fn route_with_guards<H, Out>(
+        self,
+        path: &str,
+        method: Method,
+        guards: Vec<Box<dyn Guard>>,
+        handler: H,
+    ) -> Self
+    where
+        H: Handler<Out> + Clone + Send + Sync + 'static,
+        Out: Send + 'static,
+    {
+        App {
+            router: self.router.route_with_guards(path, method, guards, handler),
+        }
+    }
+}
+
+impl Default for App<Router> {
+    fn default() -> Self {
+        App::new()
+    }
+}
+
+/// Lets a whole `App` be used wherever a `Service` is expected, e.g. passed
+/// to [`nest`] to mount it as a sub-app under another router.
+impl<R> Service<Request<Body>> for App<R>
+where
+    R: Service<Request<Body>, Response = Response<BoxBody>, Error = Error> + Clone + Send + 'static,
+    R::Future: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let mut svc = self.router.clone();
+        Box::pin(async move { svc.call(req).await })
+    }
 }
 
 impl<R> App<R>
 where
-    R: Service<Request<Body>, Response = Response<Body>, Error = Error> + Clone + Send + 'static,
+    R: Service<Request<Body>, Response = Response<BoxBody>, Error = Error> + Clone + Send + 'static,
     R::Future: Send + 'static,
 {
-    pub async fn call(&self, req: Request<Body>) -> Result<Response<Body>, Error> {
+    pub async fn call(&self, req: Request<Body>) -> Result<Response<BoxBody>, Error> {
         let mut svc = self.router.clone();
         svc.call(req).await
     }
+
+    /// Shares `state` with every handler: it's inserted into each
+    /// request's extensions before routing, so handlers pull it back out
+    /// with the [`Extension`] extractor instead of reaching for a global.
+    pub fn with_state<T>(self, state: T) -> App<AddExtension<R, T>>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        App {
+            router: AddExtension {
+                inner: self.router,
+                value: state,
+            },
+        }
+    }
+}
+
+/// A tower layer-style wrapper that inserts `value` into every request's
+/// extensions before handing off to `inner`. Built by [`App::with_state`].
+#[derive(Clone)]
+pub struct AddExtension<S, T> {
+    inner: S,
+    value: T,
+}
+
+impl<S, T> Service<Request<Body>> for AddExtension<S, T>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Error> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    T: Clone + Send + Sync + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        req.extensions_mut().insert(self.value.clone());
+        let mut inner = self.inner.clone();
+        Box::pin(async move { inner.call(req).await })
+    }
 }
 
 #[async_trait]
 pub trait FromRequest: Sized {
-    async fn from_request(req: &mut Request<Body>) -> Self;
+    type Rejection: IntoResponse;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection>;
+}
+
+impl IntoResponse for std::convert::Infallible {
+    fn into_response(self) -> Response<BoxBody> {
+        match self {}
+    }
+}
+
+/// The rejection a required-header extractor returns when the header is
+/// absent or isn't valid UTF-8: a 400 naming the missing header.
+#[derive(Debug)]
+pub struct MissingHeader(pub &'static str);
+
+impl IntoResponse for MissingHeader {
+    fn into_response(self) -> Response<BoxBody> {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("missing or invalid header: {}", self.0),
+        )
+            .into_response()
+    }
+}
+
+/// The rejection [`Extension<T>`] returns when `T` was never inserted into
+/// the request's extensions, e.g. because `App::with_state` was never
+/// called for that type. This is a server misconfiguration, not a bad
+/// request, so it maps to 500.
+#[derive(Debug)]
+pub struct MissingExtension;
+
+impl IntoResponse for MissingExtension {
+    fn into_response(self) -> Response<BoxBody> {
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+}
+
+/// Pulls a `T` previously shared via `App::with_state` out of the
+/// request's extensions, for threading a DB handle or config into
+/// handlers without a global.
+pub struct Extension<T>(pub T);
+
+#[async_trait]
+impl<T> FromRequest for Extension<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    type Rejection = MissingExtension;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        req.extensions()
+            .get::<T>()
+            .cloned()
+            .map(Extension)
+            .ok_or(MissingExtension)
+    }
+}
+
+/// The rejection returned when the body can't be read off the wire (the
+/// client disconnected mid-stream, a transfer-encoding error, etc).
+#[derive(Debug)]
+pub struct BodyReadError;
+
+impl IntoResponse for BodyReadError {
+    fn into_response(self) -> Response<BoxBody> {
+        StatusCode::BAD_REQUEST.into_response()
+    }
+}
+
+/// Why a [`BytesMaxLength`] extraction failed.
+#[derive(Debug)]
+pub enum BytesMaxLengthRejection {
+    /// More than `N` bytes arrived before the body ended.
+    TooLarge,
+    /// The body couldn't be read off the wire, same as [`BodyReadError`].
+    ReadError,
+}
+
+impl IntoResponse for BytesMaxLengthRejection {
+    fn into_response(self) -> Response<BoxBody> {
+        match self {
+            BytesMaxLengthRejection::TooLarge => StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+            BytesMaxLengthRejection::ReadError => StatusCode::BAD_REQUEST.into_response(),
+        }
+    }
+}
+
+/// Buffers the whole request body into memory, with no size limit. Use
+/// [`BytesMaxLength`] instead when the body comes from an untrusted
+/// client.
+#[async_trait]
+impl FromRequest for Bytes {
+    type Rejection = BodyReadError;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        let body = std::mem::replace(req.body_mut(), Body::empty());
+        hyper::body::to_bytes(body).await.map_err(|_| BodyReadError)
+    }
 }
 
+/// Buffers the request body, rejecting with 413 as soon as more than `N`
+/// bytes have been read, the way tower_web's `BytesMaxLength` does. This
+/// bounds memory use for a body whose `Content-Length` can't be trusted.
+pub struct BytesMaxLength<const N: usize>(pub Bytes);
+
+#[async_trait]
+impl<const N: usize> FromRequest for BytesMaxLength<N> {
+    type Rejection = BytesMaxLengthRejection;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        let mut body = std::mem::replace(req.body_mut(), Body::empty());
+        let mut collected = Vec::new();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|_| BytesMaxLengthRejection::ReadError)?;
+            collected.extend_from_slice(&chunk);
+            if collected.len() > N {
+                return Err(BytesMaxLengthRejection::TooLarge);
+            }
+        }
+        Ok(BytesMaxLength(Bytes::from(collected)))
+    }
+}
+
+/// The default cap a [`Json`] extractor buffers up to before giving up;
+/// matches `BytesMaxLength`'s 413 behavior for an oversized body.
+const JSON_MAX_LENGTH: usize = 2 * 1024 * 1024;
+
+/// Why a [`Json<T>`] extraction failed.
+#[derive(Debug)]
+pub enum JsonRejection {
+    /// The request didn't carry `Content-Type: application/json`.
+    InvalidContentType,
+    /// The body was too large to buffer.
+    TooLarge,
+    /// The buffered body wasn't valid JSON for `T`.
+    ParseError,
+}
+
+impl IntoResponse for JsonRejection {
+    fn into_response(self) -> Response<BoxBody> {
+        match self {
+            JsonRejection::InvalidContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response(),
+            JsonRejection::TooLarge => StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+            JsonRejection::ParseError => StatusCode::BAD_REQUEST.into_response(),
+        }
+    }
+}
+
+/// Buffers the body (up to [`JSON_MAX_LENGTH`]) and deserializes it as
+/// `T`, rejecting with 415 on the wrong `Content-Type` and 400 on a
+/// parse failure.
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T> FromRequest for Json<T>
+where
+    T: DeserializeOwned + Send,
+{
+    type Rejection = JsonRejection;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        let is_json = req
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.starts_with("application/json"))
+            .unwrap_or(false);
+        if !is_json {
+            return Err(JsonRejection::InvalidContentType);
+        }
+
+        let body = BytesMaxLength::<JSON_MAX_LENGTH>::from_request(req)
+            .await
+            .map_err(|_| JsonRejection::TooLarge)?;
+        serde_json::from_slice(&body.0)
+            .map(Json)
+            .map_err(|_| JsonRejection::ParseError)
+    }
+}
+
+/// The params the router captured off the request path, in pattern order
+/// (so for `/users/:id/*rest`, index 0 is `id` and index 1 is `rest`).
+/// [`Params`] and [`UrlParams`] both extract from this; it's what actually
+/// lives in the request extensions, since a `HashMap`'s iteration order
+/// doesn't track the order params were captured in.
+#[derive(Clone, Debug, Default)]
+struct CapturedParams(Vec<(String, String)>);
+
+/// The params captured from the request path by the router, e.g. for a
+/// route registered as `/users/:id` this holds `{"id": "42"}`.
+#[derive(Clone, Debug, Default)]
+pub struct Params(pub HashMap<String, String>);
+
+#[async_trait]
+impl FromRequest for Params {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        let captured = req
+            .extensions()
+            .get::<CapturedParams>()
+            .cloned()
+            .unwrap_or_default();
+        Ok(Params(captured.0.into_iter().collect()))
+    }
+}
+
+/// Path params in positional (pattern) order, for handlers that would
+/// rather destructure a tuple than look params up by name.
+pub struct UrlParams<T>(pub T);
+
+#[async_trait]
+impl FromRequest for UrlParams<(String,)> {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        let captured = req
+            .extensions()
+            .get::<CapturedParams>()
+            .cloned()
+            .unwrap_or_default();
+        let mut values = captured.0.into_iter().map(|(_, value)| value);
+        Ok(UrlParams((values.next().unwrap_or_default(),)))
+    }
+}
+
+#[async_trait]
+impl FromRequest for UrlParams<(String, String)> {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        let captured = req
+            .extensions()
+            .get::<CapturedParams>()
+            .cloned()
+            .unwrap_or_default();
+        let mut values = captured.0.into_iter().map(|(_, value)| value);
+        Ok(UrlParams((
+            values.next().unwrap_or_default(),
+            values.next().unwrap_or_default(),
+        )))
+    }
+}
+
+/// Converts a handler's return value into a `Response<B>`, the way
+/// actix's `Responder` or axum's `IntoResponse` do, so handlers don't all
+/// have to build a `Response` by hand. `B` defaults to [`BoxBody`], the
+/// type-erased body every concrete impl below produces; only the
+/// passthrough combinators (`Response<B>` itself, `(StatusCode, T)`,
+/// `Result<T, E>`) need to stay generic over it.
+pub trait IntoResponse<B = BoxBody> {
+    fn into_response(self) -> Response<B>;
+}
+
+impl<B> IntoResponse<B> for Response<B> {
+    fn into_response(self) -> Response<B> {
+        self
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self) -> Response<BoxBody> {
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/plain")
+            .body(boxed(Full::from(self)))
+            .unwrap()
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> Response<BoxBody> {
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/plain")
+            .body(boxed(Full::from(self)))
+            .unwrap()
+    }
+}
+
+impl IntoResponse for Bytes {
+    fn into_response(self) -> Response<BoxBody> {
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "application/octet-stream")
+            .body(boxed(Full::from(self)))
+            .unwrap()
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self) -> Response<BoxBody> {
+        Bytes::from(self).into_response()
+    }
+}
+
+impl IntoResponse for () {
+    fn into_response(self) -> Response<BoxBody> {
+        Response::new(boxed(Full::new(Bytes::new())))
+    }
+}
+
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> Response<BoxBody> {
+        let mut res = Response::new(boxed(Full::new(Bytes::new())));
+        *res.status_mut() = self;
+        res
+    }
+}
+
+impl<T, B> IntoResponse<B> for (StatusCode, T)
+where
+    T: IntoResponse<B>,
+{
+    fn into_response(self) -> Response<B> {
+        let (status, body) = self;
+        let mut res = body.into_response();
+        *res.status_mut() = status;
+        res
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response<BoxBody> {
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+}
+
+impl<T, E, B> IntoResponse<B> for Result<T, E>
+where
+    T: IntoResponse<B>,
+    E: IntoResponse<B>,
+{
+    fn into_response(self) -> Response<B> {
+        match self {
+            Ok(t) => t.into_response(),
+            Err(e) => e.into_response(),
+        }
+    }
+}
+
+/// `Out` is the extractor tuple a handler takes (`()`, `(T1,)`, `(T1,
+/// T2)`, ...), matched by the blanket impls below. The request stays the
+/// concrete `Body` and the response stays boxed as `BoxBody` - see the
+/// note on [`BoxBody`] for why only the response side is erased.
 #[async_trait]
 pub trait Handler<Out> {
-    async fn call(self, req: Request<Body>) -> Result<Response<Body>, Error>;
+    async fn call(self, req: Request<Body>) -> Result<Response<BoxBody>, Error>;
 }
 #[async_trait]
-impl<F, Fut> Handler<()> for F
+impl<F, Fut, Ret> Handler<()> for F
 where
     F: Fn(Request<Body>) -> Fut + Send + Sync,
-    Fut: Future<Output = Result<Response<Body>, Error>> + Send,
+    Fut: Future<Output = Ret> + Send,
+    Ret: IntoResponse,
 {
-    async fn call(self, req: Request<Body>) -> Result<Response<Body>, Error> {
-        println!("calling handler with zero extractor");
-        let res = self(req).await?;
-        Ok(res)
+    async fn call(self, req: Request<Body>) -> Result<Response<BoxBody>, Error> {
+        let ret = self(req).await;
+        Ok(ret.into_response())
     }
 }
 #[async_trait]
 #[allow(non_snake_case)]
-impl<F, Fut, T1> Handler<(T1,)> for F
+impl<F, Fut, Ret, T1> Handler<(T1,)> for F
 where
     F: Fn(Request<Body>, T1) -> Fut + Send + Sync,
-    Fut: Future<Output = Result<Response<Body>, Error>> + Send,
+    Fut: Future<Output = Ret> + Send,
+    Ret: IntoResponse,
     T1: FromRequest + Send,
 {
-    async fn call(self, mut req: Request<Body>) -> Result<Response<Body>, Error> {
-        let T1 = T1::from_request(&mut req).await;
-        let res = self(req, T1).await?;
-        Ok(res)
+    async fn call(self, mut req: Request<Body>) -> Result<Response<BoxBody>, Error> {
+        let T1 = match T1::from_request(&mut req).await {
+            Ok(value) => value,
+            Err(rejection) => return Ok(rejection.into_response()),
+        };
+        let ret = self(req, T1).await;
+        Ok(ret.into_response())
     }
 }
 
 #[async_trait]
 #[allow(non_snake_case)]
-impl<F, Fut, T1, T2> Handler<(T1, T2)> for F
+impl<F, Fut, Ret, T1, T2> Handler<(T1, T2)> for F
 where
     F: Fn(Request<Body>, T1, T2) -> Fut + Send + Sync,
-    Fut: Future<Output = Result<Response<Body>, Error>> + Send,
+    Fut: Future<Output = Ret> + Send,
+    Ret: IntoResponse,
     T1: FromRequest + Send,
     T2: FromRequest + Send,
 {
-    async fn call(self, mut req: Request<Body>) -> Result<Response<Body>, Error> {
-        let T1 = T1::from_request(&mut req).await;
-        let T2 = T2::from_request(&mut req).await;
-        let res = self(req, T1, T2).await?;
-        Ok(res)
+    async fn call(self, mut req: Request<Body>) -> Result<Response<BoxBody>, Error> {
+        let T1 = match T1::from_request(&mut req).await {
+            Ok(value) => value,
+            Err(rejection) => return Ok(rejection.into_response()),
+        };
+        let T2 = match T2::from_request(&mut req).await {
+            Ok(value) => value,
+            Err(rejection) => return Ok(rejection.into_response()),
+        };
+        let ret = self(req, T1, T2).await;
+        Ok(ret.into_response())
     }
 }
 
+/// A composable route: a single `(method, path)` spec paired with a boxed
+/// [`Handler`] and a fallback service to delegate to on a miss.
+/// `.route(a).route(b)` builds a left-folded chain of these, each trying
+/// its own spec before falling through to the one before it, down to
+/// `EmptyRouter`'s 404 at the bottom. The handler is boxed the same way
+/// `Router` boxes its table entries, so `Route` can carry any `Handler<Out>`
+/// without adding an `Out` (or `H`) type parameter to the chain.
 #[derive(Clone)]
-pub struct Route<H, F> {
-    handler: H,
+pub struct Route<F> {
+    handler: BoxedHandlerFn,
     route_spec: RouteSpec,
     fallback: F,
 }
+
+/// A compiled `(method, path)` spec, reusing the same [`Segment`] pattern
+/// matching as [`Router`] so a `Route` chain supports `:id`/`*rest` params
+/// too, not just exact paths.
 #[derive(Clone)]
 struct RouteSpec {
     method: Method,
-    spec: Bytes,
+    segments: Vec<Segment>,
 }
-// This is to validate the uri path as bytes comparisons and method used
+
 impl RouteSpec {
-    fn matches<B>(&self, req: &Request<B>) -> bool {
-        req.method() == self.method && req.uri().path().as_bytes() == self.spec
-    }
-}
-
-// impl<H, F> Service<Request<Body>> for Route<H, F>
-// where
-//     H: Service<Request<Body>, Response = Response<Body>, Error = Error> + Clone + Send + 'static,
-//     H::Future: Send,
-//     F: Service<Request<Body>, Response = Response<Body>, Error = Error> + Clone + Send + 'static,
-//     F::Future: Send,
-// {
-//     type Response = Response<Body>;
-//     type Error = Error;
-//     type Future = futures_util::future::BoxFuture<'static, Result<Self::Response, Self::Error>>;
-//     fn poll_ready(
-//         &mut self,
-//         cx: &mut std::task::Context<'_>,
-//     ) -> std::task::Poll<Result<(), Self::Error>> {
-//         Poll::Ready(Ok(()))
-//     }
-//     fn call(&mut self, req: Request<Body>) -> Self::Future {
-//         if self.route_spec.matches(&req) {}
-//     }
-// }
+    fn new(method: Method, path: &'static str) -> Self {
+        RouteSpec {
+            method,
+            segments: parse_pattern(path),
+        }
+    }
+
+    fn matches<B>(&self, req: &Request<B>) -> Option<Vec<(String, String)>> {
+        if req.method() != self.method {
+            return None;
+        }
+        match_segments(&self.segments, req.uri().path())
+    }
+}
+
+fn box_handler<H, Out>(handler: H) -> BoxedHandlerFn
+where
+    H: Handler<Out> + Clone + Send + Sync + 'static,
+    Out: Send + 'static,
+{
+    Arc::new(move |req| {
+        let handler = handler.clone();
+        Box::pin(async move { handler.call(req).await })
+    })
+}
+
+impl<F> Service<Request<Body>> for Route<F>
+where
+    F: Service<Request<Body>, Response = Response<BoxBody>, Error = Error> + Clone + Send + 'static,
+    F::Future: Send,
+{
+    type Response = Response<BoxBody>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        if let Some(captures) = self.route_spec.matches(&req) {
+            req.extensions_mut().insert(CapturedParams(captures));
+            let handler = self.handler.clone();
+            Box::pin(async move { handler(req).await })
+        } else {
+            let mut fallback = self.fallback.clone();
+            Box::pin(async move { fallback.call(req).await })
+        }
+    }
+}
+
+impl EmptyRouter {
+    /// Starts a `.route(a).route(b)` chain, falling back to this
+    /// `EmptyRouter`'s 404 once every route in the chain has missed.
+    pub fn route<H, Out>(self, path: &'static str, method: Method, handler: H) -> Route<EmptyRouter>
+    where
+        H: Handler<Out> + Clone + Send + Sync + 'static,
+        Out: Send + 'static,
+    {
+        Route {
+            handler: box_handler(handler),
+            route_spec: RouteSpec::new(method, path),
+            fallback: self,
+        }
+    }
+}
+
+impl<F> Route<F>
+where
+    F: Service<Request<Body>, Response = Response<BoxBody>, Error = Error> + Clone + Send + 'static,
+    F::Future: Send,
+{
+    /// Extends the chain with another route, trying it before falling
+    /// back to everything registered so far.
+    pub fn route<H, Out>(self, path: &'static str, method: Method, handler: H) -> Route<Self>
+    where
+        H: Handler<Out> + Clone + Send + Sync + 'static,
+        Out: Send + 'static,
+    {
+        Route {
+            handler: box_handler(handler),
+            route_spec: RouteSpec::new(method, path),
+            fallback: self,
+        }
+    }
+}
+
+/// Strips `prefix` off the request path before delegating to `inner`, so
+/// a whole sub-app built as its own `Service` can be mounted under a
+/// base path (as in axum's separate-nesting work). Requests whose path is
+/// neither `prefix` itself nor under `prefix/` (so `/apixyz` for a prefix
+/// of `/api`) fall through to a 404.
+#[derive(Clone)]
+pub struct Nested<S> {
+    prefix: Bytes,
+    inner: S,
+}
+
+pub fn nest<S>(prefix: &'static str, service: S) -> Nested<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Error> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    Nested {
+        prefix: Bytes::from_static(prefix.as_bytes()),
+        inner: service,
+    }
+}
+
+impl<S> Service<Request<Body>> for Nested<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Error> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = Response<BoxBody>;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let prefix = std::str::from_utf8(&self.prefix).unwrap_or_default();
+        let stripped = req.uri().path().strip_prefix(prefix).map(|rest| {
+            if rest.is_empty() || rest.starts_with('/') {
+                Some(rest.to_string())
+            } else {
+                None
+            }
+        });
+
+        match stripped.flatten() {
+            Some(rest) => {
+                let rest = if rest.is_empty() { "/".to_string() } else { rest };
+                let new_path_and_query = match req.uri().query() {
+                    Some(query) => format!("{}?{}", rest, query),
+                    None => rest,
+                };
+                let mut parts = req.uri().clone().into_parts();
+                parts.path_and_query = new_path_and_query.parse().ok();
+                if let Ok(uri) = http::Uri::from_parts(parts) {
+                    *req.uri_mut() = uri;
+                }
+                let mut inner = self.inner.clone();
+                Box::pin(async move { inner.call(req).await })
+            }
+            None => {
+                let mut res = Response::new(boxed(Full::new(Bytes::new())));
+                *res.status_mut() = StatusCode::NOT_FOUND;
+                Box::pin(async move { Ok(res) })
+            }
+        }
+    }
+}
@@ -1,5 +1,13 @@
-use axum::{App, Body};
-use http::{Request, StatusCode};
+use async_trait::async_trait;
+use axum::guard;
+use axum::{
+    nest, App, Body, BytesMaxLength, EmptyRouter, Extension, FromRequest, Json, MissingHeader,
+    Router, UrlParams,
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde::Deserialize;
+use tower::Service;
 
 #[tokio::test]
 async fn test_empty_router_404() {
@@ -7,5 +15,284 @@ async fn test_empty_router_404() {
     let req = Request::new(Body::empty());
     let res = app.call(req).await.unwrap();
     assert_eq!(res.status(), StatusCode::NOT_FOUND);
-    // println!("")
+}
+
+async fn capture_order(
+    _req: Request<Body>,
+    UrlParams((id, rest)): UrlParams<(String, String)>,
+) -> String {
+    format!("id={} rest={}", id, rest)
+}
+
+// Regression test for the HashMap-ordering bug: `UrlParams`'s tuple must
+// come back in pattern order (`:id` then `*rest`), not whatever order a
+// HashMap happens to iterate its entries in.
+#[tokio::test]
+async fn test_url_params_preserve_pattern_order() {
+    let app = App::new().route("/users/:id/*rest", Method::GET, capture_order);
+    let req = Request::builder()
+        .uri("/users/42/a/b")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, "id=42 rest=a/b");
+}
+
+struct UserId(String);
+
+#[async_trait]
+impl FromRequest for UserId {
+    type Rejection = MissingHeader;
+
+    async fn from_request(req: &mut Request<Body>) -> Result<Self, Self::Rejection> {
+        req.headers()
+            .get("x-user-id")
+            .and_then(|h| h.to_str().ok())
+            .map(|v| UserId(v.to_string()))
+            .ok_or(MissingHeader("x-user-id"))
+    }
+}
+
+async fn whoami(_req: Request<Body>, user: UserId) -> String {
+    user.0
+}
+
+// A handler whose extractor fails should surface the extractor's own
+// rejection (here a 400), not panic or fall through to the router's 404.
+#[tokio::test]
+async fn test_fallible_extractor_rejects_with_400() {
+    let app = App::new().route("/whoami", Method::GET, whoami);
+
+    let req = Request::builder()
+        .uri("/whoami")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+async fn echo_small_body(_req: Request<Body>, body: BytesMaxLength<8>) -> Vec<u8> {
+    body.0.to_vec()
+}
+
+#[tokio::test]
+async fn test_bytes_max_length_rejects_oversized_body_with_413() {
+    let app = App::new().route("/echo", Method::POST, echo_small_body);
+
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/echo")
+        .body(Body::from("this is way more than 8 bytes"))
+        .unwrap();
+    let res = app.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[derive(Deserialize)]
+struct Greeting {
+    name: String,
+}
+
+async fn greet_json(_req: Request<Body>, Json(greeting): Json<Greeting>) -> String {
+    format!("hello, {}", greeting.name)
+}
+
+#[tokio::test]
+async fn test_json_extractor_parses_body() {
+    let app = App::new().route("/greet", Method::POST, greet_json);
+
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/greet")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"name":"ada"}"#))
+        .unwrap();
+    let res = app.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, "hello, ada");
+}
+
+#[tokio::test]
+async fn test_json_extractor_rejects_invalid_json_with_400() {
+    let app = App::new().route("/greet", Method::POST, greet_json);
+
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/greet")
+        .header("content-type", "application/json")
+        .body(Body::from("not json"))
+        .unwrap();
+    let res = app.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+async fn route_a(_req: Request<Body>) -> &'static str {
+    "a"
+}
+
+async fn route_b(_req: Request<Body>) -> &'static str {
+    "b"
+}
+
+// A `.route(a).route(b)` chain should try every route it was built from
+// before falling through to the `EmptyRouter` 404 at the bottom.
+#[tokio::test]
+async fn test_route_chain_tries_every_route_then_falls_back_to_404() {
+    let mut chain = EmptyRouter::new()
+        .route("/a", Method::GET, route_a)
+        .route("/b", Method::GET, route_b);
+
+    let req_a = Request::builder().uri("/a").body(Body::empty()).unwrap();
+    let res_a = chain.call(req_a).await.unwrap();
+    assert_eq!(res_a.status(), StatusCode::OK);
+    assert_eq!(res_a.into_body().collect().await.unwrap().to_bytes(), "a");
+
+    let req_b = Request::builder().uri("/b").body(Body::empty()).unwrap();
+    let res_b = chain.call(req_b).await.unwrap();
+    assert_eq!(res_b.status(), StatusCode::OK);
+    assert_eq!(res_b.into_body().collect().await.unwrap().to_bytes(), "b");
+
+    let req_missing = Request::builder()
+        .uri("/missing")
+        .body(Body::empty())
+        .unwrap();
+    let res_missing = chain.call(req_missing).await.unwrap();
+    assert_eq!(res_missing.status(), StatusCode::NOT_FOUND);
+}
+
+async fn route_user(_req: Request<Body>, UrlParams((id,)): UrlParams<(String,)>) -> String {
+    format!("user {}", id)
+}
+
+// `Route`'s spec reuses `Router`'s segment matching, so `:id`/`*rest`
+// patterns work through a `.route()` chain too, not just exact paths.
+#[tokio::test]
+async fn test_route_chain_matches_path_params() {
+    let mut chain = EmptyRouter::new().route("/users/:id", Method::GET, route_user);
+
+    let req = Request::builder()
+        .uri("/users/7")
+        .body(Body::empty())
+        .unwrap();
+    let res = chain.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.into_body().collect().await.unwrap().to_bytes(),
+        "user 7"
+    );
+}
+
+async fn nested_handler(_req: Request<Body>) -> &'static str {
+    "nested"
+}
+
+#[tokio::test]
+async fn test_nest_strips_prefix_and_rejects_non_prefixed_paths() {
+    let sub = Router::new().route("/inner", Method::GET, nested_handler);
+    let mut nested = nest("/api", sub);
+
+    let req = Request::builder()
+        .uri("/api/inner")
+        .body(Body::empty())
+        .unwrap();
+    let res = nested.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.into_body().collect().await.unwrap().to_bytes(),
+        "nested"
+    );
+
+    // `/apixyz` merely starts with the "/api" prefix as a substring; it
+    // isn't a path *under* "/api" and must not match.
+    let req_almost = Request::builder()
+        .uri("/apixyz")
+        .body(Body::empty())
+        .unwrap();
+    let res_almost = nested.call(req_almost).await.unwrap();
+    assert_eq!(res_almost.status(), StatusCode::NOT_FOUND);
+}
+
+async fn handler_json(_req: Request<Body>) -> &'static str {
+    "json"
+}
+
+async fn handler_default(_req: Request<Body>) -> &'static str {
+    "default"
+}
+
+// A guard that doesn't pass shouldn't stop the router - it should keep
+// searching later routes registered for the same path and method.
+#[tokio::test]
+async fn test_failing_guard_falls_through_to_the_next_route() {
+    let app = App::new()
+        .route_with_guards(
+            "/greet",
+            Method::GET,
+            vec![Box::new(guard::header_value("accept", "application/json"))],
+            handler_json,
+        )
+        .route("/greet", Method::GET, handler_default);
+
+    let req_json = Request::builder()
+        .uri("/greet")
+        .header("accept", "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let res_json = app.call(req_json).await.unwrap();
+    assert_eq!(
+        res_json.into_body().collect().await.unwrap().to_bytes(),
+        "json"
+    );
+
+    let req_other = Request::builder()
+        .uri("/greet")
+        .header("accept", "text/plain")
+        .body(Body::empty())
+        .unwrap();
+    let res_other = app.call(req_other).await.unwrap();
+    assert_eq!(
+        res_other.into_body().collect().await.unwrap().to_bytes(),
+        "default"
+    );
+}
+
+#[derive(Clone)]
+struct AppState {
+    greeting: String,
+}
+
+async fn show_state(_req: Request<Body>, Extension(state): Extension<AppState>) -> String {
+    state.greeting
+}
+
+#[tokio::test]
+async fn test_with_state_is_extractable_via_extension() {
+    let app = App::new()
+        .route("/state", Method::GET, show_state)
+        .with_state(AppState {
+            greeting: "hi".to_string(),
+        });
+
+    let req = Request::builder()
+        .uri("/state")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.into_body().collect().await.unwrap().to_bytes(), "hi");
+}
+
+#[tokio::test]
+async fn test_missing_extension_rejects_with_500() {
+    let app = App::new().route("/state", Method::GET, show_state);
+
+    let req = Request::builder()
+        .uri("/state")
+        .body(Body::empty())
+        .unwrap();
+    let res = app.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
 }